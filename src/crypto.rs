@@ -1,120 +1,737 @@
 use aes_gcm::{
-    aead::{Aead, KeyInit, OsRng},
-    Aes256Gcm, Nonce, Key,
+    aead::{Aead as _, KeyInit as _, OsRng},
+    Aes256Gcm,
 };
 use anyhow::{anyhow, Result};
-use argon2::{Argon2, PasswordHash, PasswordHasher, PasswordVerifier, password_hash::{rand_core::RngCore, SaltString}};
+use argon2::{Algorithm, Argon2, Params, PasswordHash, PasswordHasher, PasswordVerifier, Version, password_hash::{rand_core::RngCore, SaltString}};
+use chacha20poly1305::XChaCha20Poly1305;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use base64::{Engine as _, engine::general_purpose};
+use std::io::Write;
+use std::path::Path;
+use std::str::FromStr;
 
+use sequoia_openpgp as openpgp;
+use openpgp::parse::{stream::*, Parse};
+use openpgp::policy::StandardPolicy;
+use openpgp::serialize::stream::*;
+use openpgp::Cert;
+
+/// Format version of `TimeLockedMessage`. Bump this whenever the struct
+/// shape or field semantics change, so `unlock` can tell old and new
+/// capsules apart instead of guessing.
+pub const CURRENT_VERSION: u8 = 2;
+
+/// Capsules written before `version` existed on disk are implicitly version 1.
+fn legacy_version() -> u8 {
+    1
+}
+
+/// AEAD cipher used to encrypt a capsule's content. Chosen at `lock` time
+/// via `--cipher` and recorded on the capsule so `unlock` always uses the
+/// cipher the capsule was actually written with, even after the default
+/// changes.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum AeadAlgorithm {
+    #[default]
+    Aes256Gcm,
+    XChaCha20Poly1305,
+}
+
+impl FromStr for AeadAlgorithm {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "aes256gcm" | "aes-256-gcm" => Ok(AeadAlgorithm::Aes256Gcm),
+            "xchacha20poly1305" | "xchacha20-poly1305" => Ok(AeadAlgorithm::XChaCha20Poly1305),
+            other => Err(anyhow!("Unknown cipher '{}', expected 'aes256gcm' or 'xchacha20poly1305'", other)),
+        }
+    }
+}
+
+/// Argon2 parameters used to derive a capsule's content key from a
+/// password, recorded on the capsule so key derivation stays reproducible
+/// even if `Argon2::default()`'s parameters change in a later release.
+#[derive(Serialize, Deserialize, Clone, Copy)]
+pub struct Argon2Params {
+    pub m_cost: u32,
+    pub t_cost: u32,
+    pub p_cost: u32,
+}
+
+impl Default for Argon2Params {
+    fn default() -> Self {
+        let defaults = Params::default();
+        Argon2Params {
+            m_cost: defaults.m_cost(),
+            t_cost: defaults.t_cost(),
+            p_cost: defaults.p_cost(),
+        }
+    }
+}
+
+impl From<Argon2Params> for Params {
+    fn from(p: Argon2Params) -> Self {
+        Params::new(p.m_cost, p.t_cost, p.p_cost, None).expect("recorded Argon2 params are always valid")
+    }
+}
+
+/// How the symmetric content key is protected. A message is locked with
+/// exactly one of these — never both at once.
+#[derive(Serialize, Deserialize, Clone)]
+pub enum KeyEnvelope {
+    /// Content key derived from a password the recipient already knows.
+    Password {
+        salt: String,
+        password_hash: String,
+        /// Absent on capsules written before chunk0-3; defaults to the argon2
+        /// crate's current default parameters, which match what those
+        /// capsules were derived with as long as those defaults are unchanged.
+        #[serde(default)]
+        argon2_params: Argon2Params,
+    },
+    /// Content key wrapped to one or more OpenPGP recipient certificates.
+    PublicKey { recipients: Vec<WrappedKey> },
+    /// Content key is random and never persisted anywhere; it only ever
+    /// exists in the URL-safe base64 string handed back by `lock`. The
+    /// store (and anyone who only has the JSON) never holds the key.
+    Ephemeral,
+}
+
+/// The capsule's random content key, encrypted ("wrapped") to a single
+/// OpenPGP recipient so only their secret key can recover it.
 #[derive(Serialize, Deserialize, Clone)]
+pub struct WrappedKey {
+    /// Fingerprint of the recipient cert this key was wrapped for.
+    pub recipient_fingerprint: String,
+    /// Base64-encoded OpenPGP message containing the wrapped content key.
+    pub wrapped_key: String,
+}
+
+#[derive(Serialize, Clone)]
 pub struct TimeLockedMessage {
+    /// Absent on capsules written before chunk0-3; such capsules predate
+    /// the version field entirely, so they're implicitly version 1.
+    pub version: u8,
+    /// Absent on capsules written before chunk0-3; those were always
+    /// encrypted with AES-256-GCM, the only cipher that existed then.
+    pub algorithm: AeadAlgorithm,
     pub encrypted_content: String,
     pub nonce: String,
-    pub salt: String,
-    pub password_hash: String,
+    pub key_envelope: KeyEnvelope,
     pub unlock_date: DateTime<Utc>,
     pub label: Option<String>,
     pub created_at: DateTime<Utc>,
+    /// Remaining number of times this capsule may be unlocked before it
+    /// self-destructs. `None` means it can be unlocked indefinitely.
+    pub max_reads: Option<u32>,
+}
+
+/// On-disk shape of `TimeLockedMessage`, widened to also accept the
+/// pre-chunk0-2 format: back then `salt`/`password_hash` lived as top-level
+/// fields instead of inside `key_envelope`, and `key_envelope`/`version`/
+/// `algorithm`/`max_reads` didn't exist at all yet.
+#[derive(Deserialize)]
+struct RawTimeLockedMessage {
+    #[serde(default = "legacy_version")]
+    version: u8,
+    #[serde(default)]
+    algorithm: AeadAlgorithm,
+    encrypted_content: String,
+    nonce: String,
+    #[serde(default)]
+    key_envelope: Option<KeyEnvelope>,
+    /// Only present on capsules written before chunk0-2's `key_envelope`.
+    #[serde(default)]
+    salt: Option<String>,
+    /// Only present on capsules written before chunk0-2's `key_envelope`.
+    #[serde(default)]
+    password_hash: Option<String>,
+    /// Not written by any format (current capsules keep these inside
+    /// `key_envelope`); used for a flat legacy envelope if present.
+    #[serde(default)]
+    argon2_params: Option<Argon2Params>,
+    unlock_date: DateTime<Utc>,
+    label: Option<String>,
+    created_at: DateTime<Utc>,
+    #[serde(default)]
+    max_reads: Option<u32>,
+}
+
+impl<'de> Deserialize<'de> for TimeLockedMessage {
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let raw = RawTimeLockedMessage::deserialize(deserializer)?;
+
+        let key_envelope = match raw.key_envelope {
+            Some(key_envelope) => key_envelope,
+            None => {
+                // Pre-chunk0-2 capsule: reconstruct the envelope from its
+                // flat salt/password_hash fields instead of failing outright.
+                let salt = raw.salt.ok_or_else(|| {
+                    serde::de::Error::custom("capsule has no `key_envelope` and is missing legacy field `salt`")
+                })?;
+                let password_hash = raw.password_hash.ok_or_else(|| {
+                    serde::de::Error::custom(
+                        "capsule has no `key_envelope` and is missing legacy field `password_hash`",
+                    )
+                })?;
+                KeyEnvelope::Password {
+                    salt,
+                    password_hash,
+                    argon2_params: raw.argon2_params.unwrap_or_default(),
+                }
+            }
+        };
+
+        Ok(TimeLockedMessage {
+            version: raw.version,
+            algorithm: raw.algorithm,
+            encrypted_content: raw.encrypted_content,
+            nonce: raw.nonce,
+            key_envelope,
+            unlock_date: raw.unlock_date,
+            label: raw.label,
+            created_at: raw.created_at,
+            max_reads: raw.max_reads,
+        })
+    }
 }
 
 impl TimeLockedMessage {
     pub fn new(
-        content: &str, 
-        password: &str, 
+        content: &str,
+        password: &str,
         unlock_date: DateTime<Utc>,
-        label: Option<String>
+        label: Option<String>,
+        algorithm: AeadAlgorithm,
     ) -> Result<Self> {
         // Generate random salt
         let salt = SaltString::generate(&mut OsRng);
-        
+        let argon2_params = Argon2Params::default();
+
         // Hash the password with Argon2
-        let argon2 = Argon2::default();
+        let argon2 = Argon2::new(Algorithm::Argon2id, Version::V0x13, argon2_params.into());
         let password_hash = argon2
             .hash_password(password.as_bytes(), &salt)
             .map_err(|e| anyhow!("Failed to hash password: {}", e))?
             .to_string();
-        
+
         // Derive encryption key from password
-        let key = derive_key(password, salt.as_str())?;
-        
-        // Generate random nonce
-        let mut nonce_bytes = [0u8; 12];
-        OsRng.fill_bytes(&mut nonce_bytes);
-        let nonce = Nonce::from_slice(&nonce_bytes);
-        
-        // Encrypt the content
-        let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key));
-        let encrypted_bytes = cipher
-            .encrypt(nonce, content.as_bytes())
-            .map_err(|e| anyhow!("Encryption failed: {}", e))?;
-        
-        // Encode to base64
-        let encrypted_content = general_purpose::STANDARD.encode(encrypted_bytes);
-        let nonce_str = general_purpose::STANDARD.encode(nonce_bytes);
-        
+        let key = derive_key(password, salt.as_str(), argon2_params)?;
+
+        let (nonce, encrypted_content) = encrypt_content(algorithm, &key, content)?;
+
         Ok(TimeLockedMessage {
+            version: CURRENT_VERSION,
+            algorithm,
             encrypted_content,
-            nonce: nonce_str,
-            salt: salt.to_string(),
-            password_hash,
+            nonce,
+            key_envelope: KeyEnvelope::Password { salt: salt.to_string(), password_hash, argon2_params },
             unlock_date,
             label,
             created_at: Utc::now(),
+            max_reads: None,
         })
     }
-    
+
+    /// Lock a message to one or more OpenPGP recipient certificates instead
+    /// of a shared password. Each recipient can later unlock it with their
+    /// own secret key, without ever having been told a password.
+    pub fn new_for_recipients(
+        content: &str,
+        recipient_cert_paths: &[std::path::PathBuf],
+        unlock_date: DateTime<Utc>,
+        label: Option<String>,
+        algorithm: AeadAlgorithm,
+    ) -> Result<Self> {
+        if recipient_cert_paths.is_empty() {
+            return Err(anyhow!("At least one --recipient certificate is required"));
+        }
+
+        let mut key = [0u8; 32];
+        OsRng.fill_bytes(&mut key);
+
+        let mut recipients = Vec::with_capacity(recipient_cert_paths.len());
+        for cert_path in recipient_cert_paths {
+            let cert = Cert::from_file(cert_path)
+                .map_err(|e| anyhow!("Failed to read recipient cert {:?}: {}", cert_path, e))?;
+            recipients.push(wrap_key_for_recipient(&key, &cert)?);
+        }
+
+        let (nonce, encrypted_content) = encrypt_content(algorithm, &key, content)?;
+
+        Ok(TimeLockedMessage {
+            version: CURRENT_VERSION,
+            algorithm,
+            encrypted_content,
+            nonce,
+            key_envelope: KeyEnvelope::PublicKey { recipients },
+            unlock_date,
+            label,
+            created_at: Utc::now(),
+            max_reads: None,
+        })
+    }
+
+    /// Lock a self-destructing capsule: the content key is random and never
+    /// stored anywhere, only handed back here as a URL-safe base64 string so
+    /// it can be shared out-of-band (e.g. as a `<id>#<key>` fragment link).
+    /// Returns the capsule alongside that key.
+    pub fn new_burn_after_read(
+        content: &str,
+        unlock_date: DateTime<Utc>,
+        label: Option<String>,
+        algorithm: AeadAlgorithm,
+        max_reads: Option<u32>,
+    ) -> Result<(Self, String)> {
+        if max_reads == Some(0) {
+            return Err(anyhow!("max_reads must be at least 1, a capsule with 0 reads could never be unlocked"));
+        }
+
+        let mut key = [0u8; 32];
+        OsRng.fill_bytes(&mut key);
+
+        let (nonce, encrypted_content) = encrypt_content(algorithm, &key, content)?;
+        let key_b64 = general_purpose::URL_SAFE_NO_PAD.encode(key);
+
+        let message = TimeLockedMessage {
+            version: CURRENT_VERSION,
+            algorithm,
+            encrypted_content,
+            nonce,
+            key_envelope: KeyEnvelope::Ephemeral,
+            unlock_date,
+            label,
+            created_at: Utc::now(),
+            max_reads,
+        };
+
+        Ok((message, key_b64))
+    }
+
+    /// True if this capsule is locked to recipient certificates rather than a password.
+    pub fn is_recipient_locked(&self) -> bool {
+        matches!(self.key_envelope, KeyEnvelope::PublicKey { .. })
+    }
+
+    /// True if this is a burn-after-read capsule whose key was never stored.
+    pub fn is_burn_after_read(&self) -> bool {
+        matches!(self.key_envelope, KeyEnvelope::Ephemeral)
+    }
+
+    /// This capsule after consuming one read of its `max_reads` budget, or
+    /// `None` if that was the last read and it should now be deleted.
+    /// Capsules without a read budget are returned unchanged. `created_at`
+    /// is bumped on decrement so sync (which resolves conflicts by newest
+    /// `created_at`) actually propagates the new read count.
+    pub fn after_read(&self) -> Option<Self> {
+        match self.max_reads {
+            None => Some(self.clone()),
+            Some(0) | Some(1) => None,
+            Some(n) => {
+                let mut next = self.clone();
+                next.max_reads = Some(n - 1);
+                next.created_at = Utc::now();
+                Some(next)
+            }
+        }
+    }
+
     pub fn unlock(&self, password: &str) -> Result<String> {
+        let KeyEnvelope::Password { salt, password_hash, argon2_params } = &self.key_envelope else {
+            return Err(anyhow!(
+                "This capsule is not locked with a password; use unlock_with_secret_key or unlock_with_key"
+            ));
+        };
+
         // Verify password first
-        let parsed_hash = PasswordHash::new(&self.password_hash)
+        let parsed_hash = PasswordHash::new(password_hash)
             .map_err(|e| anyhow!("Invalid password hash: {}", e))?;
-        
-        Argon2::default()
+
+        Argon2::new(Algorithm::Argon2id, Version::V0x13, (*argon2_params).into())
             .verify_password(password.as_bytes(), &parsed_hash)
             .map_err(|_| anyhow!("Invalid password"))?;
-        
+
         // Check if unlock time has passed
         if self.unlock_date > Utc::now() {
             return Err(anyhow!("Message is still time-locked"));
         }
-        
-        // Derive the same key
-        let key = derive_key(password, &self.salt)?;
-        
-        // Decode base64
-        let encrypted_bytes = general_purpose::STANDARD
-            .decode(&self.encrypted_content)
-            .map_err(|e| anyhow!("Failed to decode encrypted content: {}", e))?;
-        
-        let nonce_bytes = general_purpose::STANDARD
-            .decode(&self.nonce)
-            .map_err(|e| anyhow!("Failed to decode nonce: {}", e))?;
-        
-        let nonce = Nonce::from_slice(&nonce_bytes);
-        
-        // Decrypt
-        let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key));
-        let decrypted_bytes = cipher
-            .decrypt(nonce, encrypted_bytes.as_ref())
-            .map_err(|e| anyhow!("Decryption failed: {}", e))?;
-        
-        let content = String::from_utf8(decrypted_bytes)
-            .map_err(|e| anyhow!("Invalid UTF-8 in decrypted content: {}", e))?;
-        
-        Ok(content)
-    }
-}
-
-fn derive_key(password: &str, salt: &str) -> Result<[u8; 32]> {
-    let argon2 = Argon2::default();
+
+        // Derive the same key, using the Argon2 params the capsule was written with
+        let key = derive_key(password, salt, *argon2_params)?;
+
+        decrypt_content(self.algorithm, &key, &self.nonce, &self.encrypted_content)
+    }
+
+    /// Unlock a capsule that was locked to a recipient's OpenPGP certificate,
+    /// using that recipient's secret key.
+    pub fn unlock_with_secret_key(&self, secret_key_path: &Path, passphrase: Option<&str>) -> Result<String> {
+        let KeyEnvelope::PublicKey { recipients } = &self.key_envelope else {
+            return Err(anyhow!("This capsule is not locked to a recipient key; use unlock or unlock_with_key"));
+        };
+
+        if self.unlock_date > Utc::now() {
+            return Err(anyhow!("Message is still time-locked"));
+        }
+
+        let cert = Cert::from_file(secret_key_path)
+            .map_err(|e| anyhow!("Failed to read secret key {:?}: {}", secret_key_path, e))?;
+
+        let key = recipients
+            .iter()
+            .find_map(|wrapped| unwrap_key_with_secret(wrapped, &cert, passphrase).ok())
+            .ok_or_else(|| anyhow!("This secret key cannot unwrap any recipient entry on this capsule"))?;
+
+        decrypt_content(self.algorithm, &key, &self.nonce, &self.encrypted_content)
+    }
+
+    /// Unlock a burn-after-read capsule using the key handed back by
+    /// `new_burn_after_read` (the part after the `#` in its share link).
+    pub fn unlock_with_key(&self, key_b64: &str) -> Result<String> {
+        if !matches!(self.key_envelope, KeyEnvelope::Ephemeral) {
+            return Err(anyhow!("This capsule is not a burn-after-read capsule; use unlock or unlock_with_secret_key"));
+        }
+
+        if self.unlock_date > Utc::now() {
+            return Err(anyhow!("Message is still time-locked"));
+        }
+
+        let key_bytes = general_purpose::URL_SAFE_NO_PAD
+            .decode(key_b64)
+            .map_err(|e| anyhow!("Invalid key: {}", e))?;
+        let key: [u8; 32] = key_bytes
+            .try_into()
+            .map_err(|_| anyhow!("Key must decode to exactly 32 bytes"))?;
+
+        decrypt_content(self.algorithm, &key, &self.nonce, &self.encrypted_content)
+    }
+}
+
+fn encrypt_content(algorithm: AeadAlgorithm, key: &[u8; 32], content: &str) -> Result<(String, String)> {
+    let (nonce_bytes, encrypted_bytes) = match algorithm {
+        AeadAlgorithm::Aes256Gcm => {
+            let mut nonce_bytes = [0u8; 12];
+            OsRng.fill_bytes(&mut nonce_bytes);
+            let cipher = Aes256Gcm::new_from_slice(key).map_err(|e| anyhow!("Invalid key: {}", e))?;
+            let encrypted = cipher
+                .encrypt(aes_gcm::Nonce::from_slice(&nonce_bytes), content.as_bytes())
+                .map_err(|e| anyhow!("Encryption failed: {}", e))?;
+            (nonce_bytes.to_vec(), encrypted)
+        }
+        AeadAlgorithm::XChaCha20Poly1305 => {
+            let mut nonce_bytes = [0u8; 24];
+            OsRng.fill_bytes(&mut nonce_bytes);
+            let cipher = XChaCha20Poly1305::new_from_slice(key).map_err(|e| anyhow!("Invalid key: {}", e))?;
+            let encrypted = cipher
+                .encrypt(chacha20poly1305::XNonce::from_slice(&nonce_bytes), content.as_bytes())
+                .map_err(|e| anyhow!("Encryption failed: {}", e))?;
+            (nonce_bytes.to_vec(), encrypted)
+        }
+    };
+
+    let encrypted_content = general_purpose::STANDARD.encode(encrypted_bytes);
+    let nonce_str = general_purpose::STANDARD.encode(nonce_bytes);
+
+    Ok((nonce_str, encrypted_content))
+}
+
+fn decrypt_content(algorithm: AeadAlgorithm, key: &[u8; 32], nonce_b64: &str, encrypted_content_b64: &str) -> Result<String> {
+    let encrypted_bytes = general_purpose::STANDARD
+        .decode(encrypted_content_b64)
+        .map_err(|e| anyhow!("Failed to decode encrypted content: {}", e))?;
+
+    let nonce_bytes = general_purpose::STANDARD
+        .decode(nonce_b64)
+        .map_err(|e| anyhow!("Failed to decode nonce: {}", e))?;
+
+    let decrypted_bytes = match algorithm {
+        AeadAlgorithm::Aes256Gcm => {
+            let cipher = Aes256Gcm::new_from_slice(key).map_err(|e| anyhow!("Invalid key: {}", e))?;
+            cipher
+                .decrypt(aes_gcm::Nonce::from_slice(&nonce_bytes), encrypted_bytes.as_ref())
+                .map_err(|e| anyhow!("Decryption failed: {}", e))?
+        }
+        AeadAlgorithm::XChaCha20Poly1305 => {
+            let cipher = XChaCha20Poly1305::new_from_slice(key).map_err(|e| anyhow!("Invalid key: {}", e))?;
+            cipher
+                .decrypt(chacha20poly1305::XNonce::from_slice(&nonce_bytes), encrypted_bytes.as_ref())
+                .map_err(|e| anyhow!("Decryption failed: {}", e))?
+        }
+    };
+
+    String::from_utf8(decrypted_bytes).map_err(|e| anyhow!("Invalid UTF-8 in decrypted content: {}", e))
+}
+
+fn derive_key(password: &str, salt: &str, params: Argon2Params) -> Result<[u8; 32]> {
+    let argon2 = Argon2::new(Algorithm::Argon2id, Version::V0x13, params.into());
     let salt_string = SaltString::from_b64(salt)
         .map_err(|e| anyhow!("Invalid salt: {}", e))?;
-    
+
     let mut key = [0u8; 32];
     argon2
         .hash_password_into(password.as_bytes(), salt_string.as_str().as_bytes(), &mut key)
         .map_err(|e| anyhow!("Key derivation failed: {}", e))?;
-    
+
+    Ok(key)
+}
+
+/// Encrypt the 32-byte content key to a single OpenPGP recipient certificate.
+fn wrap_key_for_recipient(key: &[u8; 32], cert: &Cert) -> Result<WrappedKey> {
+    let policy = StandardPolicy::new();
+    let recipient = cert
+        .keys()
+        .with_policy(&policy, None)
+        .supported()
+        .alive()
+        .revoked(false)
+        .for_transport_encryption()
+        .next()
+        .ok_or_else(|| anyhow!("Cert {} has no usable encryption subkey", cert.fingerprint()))?;
+
+    let mut wrapped = Vec::new();
+    let message = Message::new(&mut wrapped);
+    let message = Encryptor2::for_recipients(message, vec![recipient]).build()?;
+    let mut message = LiteralWriter::new(message).build()?;
+    message.write_all(key)?;
+    message.finalize()?;
+
+    Ok(WrappedKey {
+        recipient_fingerprint: cert.fingerprint().to_string(),
+        wrapped_key: general_purpose::STANDARD.encode(wrapped),
+    })
+}
+
+struct ContentKeyDecryptor<'a> {
+    cert: &'a Cert,
+    policy: &'a StandardPolicy<'a>,
+    passphrase: Option<&'a str>,
+}
+
+impl<'a> VerificationHelper for ContentKeyDecryptor<'a> {
+    fn get_certs(&mut self, _ids: &[openpgp::KeyHandle]) -> openpgp::Result<Vec<Cert>> {
+        Ok(vec![self.cert.clone()])
+    }
+
+    fn check(&mut self, _structure: MessageStructure) -> openpgp::Result<()> {
+        // Recipient-key mode only needs confidentiality, not a signature chain.
+        Ok(())
+    }
+}
+
+impl<'a> DecryptionHelper for ContentKeyDecryptor<'a> {
+    fn decrypt<D>(
+        &mut self,
+        pkesks: &[openpgp::packet::PKESK],
+        _skesks: &[openpgp::packet::SKESK],
+        sym_algo: Option<openpgp::types::SymmetricAlgorithm>,
+        mut decrypt: D,
+    ) -> openpgp::Result<Option<openpgp::Fingerprint>>
+    where
+        D: FnMut(openpgp::types::SymmetricAlgorithm, &openpgp::crypto::SessionKey) -> bool,
+    {
+        for key in self
+            .cert
+            .keys()
+            .with_policy(self.policy, None)
+            .supported()
+            .alive()
+            .revoked(false)
+            .for_transport_encryption()
+            .secret()
+        {
+            let mut unlocked = key.key().clone();
+            if let Some(passphrase) = self.passphrase {
+                unlocked = unlocked
+                    .decrypt_secret(&passphrase.into())?;
+            }
+            let mut keypair = unlocked.into_keypair()?;
+
+            for pkesk in pkesks {
+                if let Some((algo, session_key)) = pkesk.decrypt(&mut keypair, sym_algo) {
+                    if decrypt(algo, &session_key) {
+                        return Ok(Some(key.fingerprint()));
+                    }
+                }
+            }
+        }
+
+        Err(anyhow!("No matching secret key could decrypt this wrapped key"))
+    }
+}
+
+/// Recover the 32-byte content key from a `WrappedKey`, using the given
+/// OpenPGP secret-key cert (optionally passphrase-protected).
+fn unwrap_key_with_secret(wrapped: &WrappedKey, cert: &Cert, passphrase: Option<&str>) -> Result<[u8; 32]> {
+    let policy = StandardPolicy::new();
+    let wrapped_bytes = general_purpose::STANDARD
+        .decode(&wrapped.wrapped_key)
+        .map_err(|e| anyhow!("Failed to decode wrapped key: {}", e))?;
+
+    let helper = ContentKeyDecryptor { cert, policy: &policy, passphrase };
+    let mut decryptor = DecryptorBuilder::from_bytes(&wrapped_bytes)?
+        .with_policy(&policy, None, helper)
+        .map_err(|e| anyhow!("Failed to decrypt wrapped key: {}", e))?;
+
+    let mut key = [0u8; 32];
+    std::io::Read::read_exact(&mut decryptor, &mut key)
+        .map_err(|e| anyhow!("Wrapped key had unexpected length: {}", e))?;
+
     Ok(key)
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn current_format_round_trips_through_serde() {
+        let original = TimeLockedMessage::new(
+            "hello from right now",
+            "correct horse battery staple",
+            Utc::now() - chrono::Duration::seconds(1),
+            None,
+            AeadAlgorithm::Aes256Gcm,
+        )
+        .unwrap();
+
+        let json = serde_json::to_string(&original).unwrap();
+        let round_tripped: TimeLockedMessage = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(round_tripped.version, CURRENT_VERSION);
+        assert_eq!(round_tripped.algorithm, AeadAlgorithm::Aes256Gcm);
+        assert_eq!(
+            round_tripped.unlock("correct horse battery staple").unwrap(),
+            "hello from right now"
+        );
+    }
+
+    #[test]
+    fn pre_chunk0_2_capsule_with_top_level_salt_still_unlocks() {
+        let password = "correct horse battery staple";
+
+        let salt = SaltString::generate(&mut OsRng);
+        let password_hash = Argon2::default()
+            .hash_password(password.as_bytes(), &salt)
+            .unwrap()
+            .to_string();
+        let key = derive_key(password, salt.as_str(), Argon2Params::default()).unwrap();
+        let (nonce, encrypted_content) =
+            encrypt_content(AeadAlgorithm::Aes256Gcm, &key, "hello from before key_envelope existed").unwrap();
+
+        // This is the actual pre-chunk0-2 on-disk shape: salt/password_hash
+        // sit at the top level, there's no key_envelope wrapper, and no
+        // version/algorithm/max_reads fields exist at all.
+        let json = format!(
+            r#"{{
+                "encrypted_content": {encrypted_content:?},
+                "nonce": {nonce:?},
+                "salt": {salt:?},
+                "password_hash": {password_hash:?},
+                "unlock_date": "2020-01-01T00:00:00Z",
+                "label": null,
+                "created_at": "2020-01-01T00:00:00Z"
+            }}"#,
+            encrypted_content = encrypted_content,
+            nonce = nonce,
+            salt = salt.as_str(),
+            password_hash = password_hash,
+        );
+
+        let legacy: TimeLockedMessage = serde_json::from_str(&json).unwrap();
+        assert_eq!(legacy.version, 1);
+        assert_eq!(legacy.algorithm, AeadAlgorithm::Aes256Gcm);
+        assert!(legacy.max_reads.is_none());
+        assert_eq!(
+            legacy.unlock(password).unwrap(),
+            "hello from before key_envelope existed"
+        );
+    }
+
+    #[test]
+    fn xchacha20poly1305_round_trips() {
+        let message = TimeLockedMessage::new(
+            "rolling cipher",
+            "hunter2",
+            Utc::now() - chrono::Duration::seconds(1),
+            None,
+            AeadAlgorithm::XChaCha20Poly1305,
+        )
+        .unwrap();
+
+        assert_eq!(message.unlock("hunter2").unwrap(), "rolling cipher");
+    }
+
+    #[test]
+    fn burn_after_read_decrements_then_self_destructs() {
+        let (message, key) = TimeLockedMessage::new_burn_after_read(
+            "ephemeral secret",
+            Utc::now() - chrono::Duration::seconds(1),
+            None,
+            AeadAlgorithm::Aes256Gcm,
+            Some(2),
+        )
+        .unwrap();
+
+        assert_eq!(message.unlock_with_key(&key).unwrap(), "ephemeral secret");
+
+        let after_first_read = message.after_read().expect("one read remaining");
+        assert_eq!(after_first_read.max_reads, Some(1));
+
+        let after_second_read = after_first_read.after_read();
+        assert!(after_second_read.is_none());
+    }
+
+    #[test]
+    fn burn_after_read_rejects_zero_max_reads() {
+        let result = TimeLockedMessage::new_burn_after_read(
+            "never readable",
+            Utc::now(),
+            None,
+            AeadAlgorithm::Aes256Gcm,
+            Some(0),
+        );
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn recipient_locked_round_trips_with_matching_secret_key() {
+        use openpgp::cert::CertBuilder;
+        use openpgp::serialize::Serialize as _;
+        use uuid::Uuid;
+
+        let (cert, _revocation) = CertBuilder::general_purpose(None, Some("capsule-test@example.com"))
+            .generate()
+            .unwrap();
+
+        let mut secret_bytes = Vec::new();
+        cert.as_tsk().serialize(&mut secret_bytes).unwrap();
+        let mut public_bytes = Vec::new();
+        cert.serialize(&mut public_bytes).unwrap();
+
+        let secret_path = std::env::temp_dir().join(format!("timecapsule-test-secret-{}.pgp", Uuid::new_v4()));
+        let public_path = std::env::temp_dir().join(format!("timecapsule-test-public-{}.pgp", Uuid::new_v4()));
+        std::fs::write(&secret_path, &secret_bytes).unwrap();
+        std::fs::write(&public_path, &public_bytes).unwrap();
+
+        let message = TimeLockedMessage::new_for_recipients(
+            "for your eyes only",
+            &[public_path.clone()],
+            Utc::now() - chrono::Duration::seconds(1),
+            None,
+            AeadAlgorithm::Aes256Gcm,
+        )
+        .unwrap();
+
+        let content = message.unlock_with_secret_key(&secret_path, None).unwrap();
+        assert_eq!(content, "for your eyes only");
+
+        let _ = std::fs::remove_file(&secret_path);
+        let _ = std::fs::remove_file(&public_path);
+    }
+}
@@ -6,8 +6,10 @@ use std::path::PathBuf;
 
 mod crypto;
 mod storage;
+mod sync;
 
-use crypto::TimeLockedMessage;
+use crypto::{AeadAlgorithm, TimeLockedMessage};
+use storage::StoreConfig;
 
 
 #[derive(Parser)]
@@ -16,6 +18,43 @@ use crypto::TimeLockedMessage;
 struct Cli {
     #[command(subcommand)]
     command: Commands,
+
+    /// Storage backend to use for capsules
+    #[arg(long, global = true, default_value = "local")]
+    store: String,
+
+    /// S3-compatible endpoint URL (for MinIO/Garage, leave unset for AWS S3)
+    #[arg(long, global = true, env = "TIMECAPSULE_S3_ENDPOINT")]
+    s3_endpoint: Option<String>,
+
+    /// Bucket to store capsules in when using --store s3
+    #[arg(long, global = true, env = "TIMECAPSULE_S3_BUCKET")]
+    s3_bucket: Option<String>,
+
+    /// Region to use when using --store s3
+    #[arg(long, global = true, env = "TIMECAPSULE_S3_REGION", default_value = "us-east-1")]
+    s3_region: String,
+
+    /// Access key to use when using --store s3
+    #[arg(long, global = true, env = "TIMECAPSULE_S3_ACCESS_KEY")]
+    s3_access_key: Option<String>,
+
+    /// Secret key to use when using --store s3
+    #[arg(long, global = true, env = "TIMECAPSULE_S3_SECRET_KEY")]
+    s3_secret_key: Option<String>,
+}
+
+impl Cli {
+    fn store_config(&self) -> StoreConfig {
+        StoreConfig {
+            backend: self.store.clone(),
+            s3_endpoint: self.s3_endpoint.clone(),
+            s3_bucket: self.s3_bucket.clone(),
+            s3_region: self.s3_region.clone(),
+            s3_access_key: self.s3_access_key.clone(),
+            s3_secret_key: self.s3_secret_key.clone(),
+        }
+    }
 }
 
 #[derive(Subcommand)]
@@ -41,114 +80,234 @@ enum Commands {
         /// Output file (optional, defaults to storage directory)
         #[arg(short, long)]
         output: Option<PathBuf>,
+
+        /// Lock to one or more OpenPGP recipient certificates instead of a password
+        /// (can be repeated). Mutually exclusive with the password prompt.
+        #[arg(short, long)]
+        recipient: Vec<PathBuf>,
+
+        /// AEAD cipher to encrypt the content with: "aes256gcm" (default) or "xchacha20poly1305"
+        #[arg(long, default_value = "aes256gcm")]
+        cipher: AeadAlgorithm,
+
+        /// Create a self-destructing capsule: the key is never stored anywhere,
+        /// only handed back once as part of a shareable `<id>#<key>` link.
+        /// Mutually exclusive with --recipient and the password prompt.
+        #[arg(long)]
+        burn: bool,
+
+        /// Number of times a --burn capsule may be unlocked before it self-destructs
+        #[arg(long, default_value_t = 1)]
+        max_reads: u32,
     },
     /// Try to unlock a message
     Unlock {
-        /// Message ID or file path
+        /// Message ID (a burn-after-read link's "<id>#<key>" works here too)
         #[arg(short, long)]
         id: Option<String>,
-        
+
         /// File path to unlock
         #[arg(short, long)]
         file: Option<PathBuf>,
+
+        /// Recipient's OpenPGP secret key, required to unlock a capsule
+        /// that was locked with --recipient
+        #[arg(short, long)]
+        secret_key: Option<PathBuf>,
+
+        /// Key for a burn-after-read capsule (the part after '#' in its share link)
+        #[arg(short, long)]
+        key: Option<String>,
     },
     /// List all locked messages
     List,
     /// Check if any messages are ready to unlock
     Check,
+    /// Sync the local capsule store with a remote server. Every record is
+    /// sealed with a device-local key before upload, so the server never
+    /// sees plaintext.
+    Sync {
+        /// Remote sync server base URL
+        #[arg(short, long, env = "TIMECAPSULE_SYNC_ENDPOINT")]
+        endpoint: String,
+    },
 }
 
-fn main() -> Result<()> {
+#[tokio::main]
+async fn main() -> Result<()> {
     let cli = Cli::parse();
+    let store_config = cli.store_config();
 
     match cli.command {
-        Commands::Lock { message, file, date, label, output } => {
+        Commands::Lock { message, file, date, label, output, recipient, cipher, burn, max_reads } => {
             let content = get_message_content(message, file)?;
             let unlock_date = parse_date(&date)?;
-            
+
             if unlock_date <= Utc::now() {
                 return Err(anyhow!("Unlock date must be in the future"));
             }
-            
-            let password = rpassword::prompt_password("Enter password to encrypt the message: ")?;
-            if password.trim().is_empty() {
-                return Err(anyhow!("Password cannot be empty"));
+
+            if burn && !recipient.is_empty() {
+                return Err(anyhow!("--burn and --recipient are mutually exclusive"));
             }
-            
-            let locked_msg = TimeLockedMessage::new(&content, &password, unlock_date, label)?;
-            
+
+            let (locked_msg, burn_key) = if burn {
+                let (msg, key) =
+                    TimeLockedMessage::new_burn_after_read(&content, unlock_date, label, cipher, Some(max_reads))?;
+                (msg, Some(key))
+            } else if !recipient.is_empty() {
+                (TimeLockedMessage::new_for_recipients(&content, &recipient, unlock_date, label, cipher)?, None)
+            } else {
+                let password = rpassword::prompt_password("Enter password to encrypt the message: ")?;
+                if password.trim().is_empty() {
+                    return Err(anyhow!("Password cannot be empty"));
+                }
+                (TimeLockedMessage::new(&content, &password, unlock_date, label, cipher)?, None)
+            };
+
             let id = if let Some(output_path) = output {
                 storage::save_to_file(&locked_msg, &output_path)?;
                 output_path.file_stem().unwrap().to_string_lossy().to_string()
             } else {
-                storage::save_message(&locked_msg)?
+                let store = storage::build_store(&store_config).await?;
+                store.put(&locked_msg).await?
             };
-            
+
             println!("✅ Message locked successfully!");
             println!("📦 Message ID: {}", id);
             println!("🔓 Unlock date: {}", unlock_date.format("%Y-%m-%d %H:%M:%S UTC"));
             println!("⏰ Time remaining: {}", format_duration(unlock_date - Utc::now()));
+
+            if let Some(key) = burn_key {
+                println!("🔥 Burn-after-read capsule — this link is the only copy of the key, share it out-of-band:");
+                println!("   timecapsule://{}#{}", id, key);
+                println!("   Unlockable {} time(s) before it self-destructs", max_reads);
+            }
         }
-        
-        Commands::Unlock { id, file } => {
-            let locked_msg = if let Some(file_path) = file {
-                storage::load_from_file(&file_path)?
-            } else if let Some(message_id) = id {
-                storage::load_message(&message_id)?
+
+        Commands::Unlock { id, file, secret_key, key } => {
+            // A burn-after-read share link ("timecapsule://<id>#<key>" or
+            // bare "<id>#<key>") may be passed whole via --id.
+            let (id, key) = match id {
+                Some(raw) => {
+                    let raw = raw.strip_prefix("timecapsule://").map(str::to_string).unwrap_or(raw);
+                    match raw.split_once('#') {
+                        Some((id_part, key_part)) => (Some(id_part.to_string()), key.or_else(|| Some(key_part.to_string()))),
+                        None => (Some(raw), key),
+                    }
+                }
+                None => (None, key),
+            };
+
+            let locked_msg = if let Some(file_path) = &file {
+                storage::load_from_file(file_path)?
+            } else if let Some(message_id) = &id {
+                let store = storage::build_store(&store_config).await?;
+                store.get(message_id).await?
             } else {
                 return Err(anyhow!("Must specify either --id or --file"));
             };
-            
+
             if locked_msg.unlock_date > Utc::now() {
                 println!("🔒 Message is still locked!");
                 println!("🔓 Unlock date: {}", locked_msg.unlock_date.format("%Y-%m-%d %H:%M:%S UTC"));
                 println!("⏰ Time remaining: {}", format_duration(locked_msg.unlock_date - Utc::now()));
                 return Ok(());
             }
-            
-            let password = rpassword::prompt_password("Enter password to decrypt the message: ")?;
-            
-            match locked_msg.unlock(&password) {
+
+            let unlocked = if locked_msg.is_burn_after_read() {
+                let key = key.ok_or_else(|| {
+                    anyhow!("This capsule is burn-after-read; pass --key <key> or an --id \"<id>#<key>\" link")
+                })?;
+                locked_msg.unlock_with_key(&key)
+            } else if locked_msg.is_recipient_locked() {
+                let secret_key_path = secret_key
+                    .ok_or_else(|| anyhow!("This capsule is locked to a recipient; pass --secret-key <file>"))?;
+                let passphrase = rpassword::prompt_password(
+                    "Enter secret key passphrase (leave empty if none): ",
+                )?;
+                let passphrase = (!passphrase.is_empty()).then_some(passphrase.as_str());
+                locked_msg.unlock_with_secret_key(&secret_key_path, passphrase)
+            } else {
+                let password = rpassword::prompt_password("Enter password to decrypt the message: ")?;
+                locked_msg.unlock(&password)
+            };
+
+            match unlocked {
                 Ok(content) => {
                     println!("🎉 Message unlocked successfully!");
                     println!("📄 Content:");
                     println!("{}", "=".repeat(50));
                     println!("{}", content);
                     println!("{}", "=".repeat(50));
+
+                    if locked_msg.is_burn_after_read() {
+                        match locked_msg.after_read() {
+                            Some(updated) => {
+                                if let Some(file_path) = &file {
+                                    storage::save_to_file(&updated, file_path)?;
+                                } else if let Some(message_id) = &id {
+                                    let store = storage::build_store(&store_config).await?;
+                                    store.update(message_id, &updated).await?;
+                                }
+                                if let Some(remaining) = updated.max_reads {
+                                    println!("🔥 {} read(s) remaining before this capsule self-destructs", remaining);
+                                }
+                            }
+                            None => {
+                                if let Some(file_path) = &file {
+                                    fs::remove_file(file_path)?;
+                                } else if let Some(message_id) = &id {
+                                    let store = storage::build_store(&store_config).await?;
+                                    store.delete(message_id).await?;
+                                    // Best-effort: the capsule is already gone locally, so a
+                                    // failure to record the tombstone shouldn't fail a command
+                                    // that already succeeded — it just means the next `sync`
+                                    // run won't know to delete it remotely yet.
+                                    if let Err(e) = sync::record_tombstone(message_id) {
+                                        eprintln!("Warning: failed to record deletion for sync: {}", e);
+                                    }
+                                }
+                                println!("🔥 This was the last read — the capsule has self-destructed");
+                            }
+                        }
+                    }
                 }
                 Err(e) => {
                     println!("❌ Failed to unlock message: {}", e);
                 }
             }
         }
-        
+
         Commands::List => {
-            let messages = storage::list_messages()?;
+            let store = storage::build_store(&store_config).await?;
+            let messages = store.list().await?;
             if messages.is_empty() {
                 println!("📭 No locked messages found");
                 return Ok(());
             }
-            
+
             println!("📦 Locked Messages:");
             println!("{}", "=".repeat(80));
             for (id, msg) in messages {
                 let status = if msg.unlock_date <= Utc::now() { "🔓 READY" } else { "🔒 LOCKED" };
                 let label = msg.label.as_deref().unwrap_or("(no label)");
-                println!("ID: {} | {} | {} | {}", 
-                    id, 
-                    status, 
+                println!("ID: {} | {} | {} | {}",
+                    id,
+                    status,
                     msg.unlock_date.format("%Y-%m-%d %H:%M UTC"),
                     label
                 );
             }
         }
-        
+
         Commands::Check => {
-            let messages = storage::list_messages()?;
+            let store = storage::build_store(&store_config).await?;
+            let messages = store.list().await?;
             let ready_messages: Vec<_> = messages.into_iter()
                 .filter(|(_, msg)| msg.unlock_date <= Utc::now())
                 .collect();
-            
+
             if ready_messages.is_empty() {
                 println!("⏰ No messages are ready to unlock yet");
             } else {
@@ -160,8 +319,17 @@ fn main() -> Result<()> {
                 println!("\nUse 'timelock unlock --id <ID>' to unlock them");
             }
         }
+
+        Commands::Sync { endpoint } => {
+            let store = storage::build_store(&store_config).await?;
+            let report = sync::run(store.as_ref(), &endpoint).await?;
+
+            println!("🔄 Synced with {}", endpoint);
+            println!("⬆️  Pushed {} capsule(s)", report.pushed);
+            println!("⬇️  Pulled {} capsule(s)", report.pulled);
+        }
     }
-    
+
     Ok(())
 }
 
@@ -0,0 +1,82 @@
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use uuid::Uuid;
+
+use super::{load_from_file, save_to_file, CapsuleStore};
+use crate::crypto::TimeLockedMessage;
+
+/// Default backend: each capsule is a JSON file under `~/.timecapsule`.
+pub struct LocalStore {
+    dir: PathBuf,
+}
+
+impl LocalStore {
+    pub fn new() -> Result<Self> {
+        let home_dir = dirs::home_dir().ok_or_else(|| anyhow!("Could not find home directory"))?;
+        let dir = home_dir.join(".timecapsule");
+
+        if !dir.exists() {
+            fs::create_dir_all(&dir)?;
+        }
+
+        Ok(LocalStore { dir })
+    }
+
+    fn path_for(&self, id: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", id))
+    }
+}
+
+#[async_trait]
+impl CapsuleStore for LocalStore {
+    async fn put(&self, message: &TimeLockedMessage) -> Result<String> {
+        let id = Uuid::new_v4().to_string();
+        save_to_file(message, &self.path_for(&id))?;
+        Ok(id)
+    }
+
+    async fn get(&self, id: &str) -> Result<TimeLockedMessage> {
+        load_from_file(&self.path_for(id))
+    }
+
+    async fn list(&self) -> Result<HashMap<String, TimeLockedMessage>> {
+        let mut messages = HashMap::new();
+
+        if !self.dir.exists() {
+            return Ok(messages);
+        }
+
+        for entry in fs::read_dir(&self.dir)? {
+            let entry = entry?;
+            let path = entry.path();
+
+            if path.extension().map_or(false, |ext| ext == "json") {
+                if let Some(file_stem) = path.file_stem() {
+                    let id = file_stem.to_string_lossy().to_string();
+                    match load_from_file(&path) {
+                        Ok(message) => {
+                            messages.insert(id, message);
+                        }
+                        Err(e) => {
+                            eprintln!("Warning: Failed to load message {}: {}", id, e);
+                        }
+                    }
+                }
+            }
+        }
+
+        Ok(messages)
+    }
+
+    async fn update(&self, id: &str, message: &TimeLockedMessage) -> Result<()> {
+        save_to_file(message, &self.path_for(id))
+    }
+
+    async fn delete(&self, id: &str) -> Result<()> {
+        let path = self.path_for(id);
+        fs::remove_file(&path).map_err(|e| anyhow!("Failed to delete {:?}: {}", path, e))
+    }
+}
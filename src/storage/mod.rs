@@ -0,0 +1,98 @@
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use std::collections::HashMap;
+
+use crate::crypto::TimeLockedMessage;
+
+mod local;
+mod s3;
+
+pub use local::LocalStore;
+pub use s3::S3Store;
+
+/// Backend-agnostic persistence for time-locked capsules.
+///
+/// Implementations decide where the encrypted capsules actually live
+/// (a local directory, an S3-compatible bucket, ...). Callers in
+/// `main.rs` only ever talk to this trait so the CLI stays storage-agnostic.
+#[async_trait]
+pub trait CapsuleStore: Send + Sync {
+    /// Persist a new capsule and return the id it was stored under.
+    async fn put(&self, message: &TimeLockedMessage) -> Result<String>;
+
+    /// Load a previously stored capsule by id.
+    async fn get(&self, id: &str) -> Result<TimeLockedMessage>;
+
+    /// List every capsule currently in the store, keyed by id.
+    async fn list(&self) -> Result<HashMap<String, TimeLockedMessage>>;
+
+    /// Overwrite an existing capsule in place (e.g. after decrementing a
+    /// burn-after-read capsule's remaining read count).
+    async fn update(&self, id: &str, message: &TimeLockedMessage) -> Result<()>;
+
+    /// Remove a capsule from the store.
+    async fn delete(&self, id: &str) -> Result<()>;
+}
+
+/// Options needed to construct any of the supported stores, gathered from
+/// CLI flags / environment variables in `main.rs`.
+pub struct StoreConfig {
+    pub backend: String,
+    pub s3_endpoint: Option<String>,
+    pub s3_bucket: Option<String>,
+    pub s3_region: String,
+    pub s3_access_key: Option<String>,
+    pub s3_secret_key: Option<String>,
+}
+
+/// Build the store selected by `--store` (defaults to the local directory store).
+pub async fn build_store(config: &StoreConfig) -> Result<Box<dyn CapsuleStore>> {
+    match config.backend.as_str() {
+        "local" => Ok(Box::new(LocalStore::new()?)),
+        "s3" => {
+            let bucket = config
+                .s3_bucket
+                .clone()
+                .ok_or_else(|| anyhow!("--s3-bucket (or TIMECAPSULE_S3_BUCKET) is required for --store s3"))?;
+            let access_key = config
+                .s3_access_key
+                .clone()
+                .ok_or_else(|| anyhow!("--s3-access-key (or TIMECAPSULE_S3_ACCESS_KEY) is required for --store s3"))?;
+            let secret_key = config
+                .s3_secret_key
+                .clone()
+                .ok_or_else(|| anyhow!("--s3-secret-key (or TIMECAPSULE_S3_SECRET_KEY) is required for --store s3"))?;
+
+            let store = S3Store::new(
+                config.s3_endpoint.clone(),
+                bucket,
+                config.s3_region.clone(),
+                access_key,
+                secret_key,
+            )
+            .await?;
+            Ok(Box::new(store))
+        }
+        other => Err(anyhow!("Unknown store backend '{}', expected 'local' or 's3'", other)),
+    }
+}
+
+/// Save a capsule straight to an arbitrary file path, bypassing whichever
+/// store is active. Used by `lock --output` and `unlock --file`.
+pub fn save_to_file(message: &TimeLockedMessage, file_path: &std::path::Path) -> Result<()> {
+    let json = serde_json::to_string_pretty(message)?;
+    std::fs::write(file_path, json)?;
+    Ok(())
+}
+
+/// Load a capsule straight from an arbitrary file path, bypassing whichever
+/// store is active. Used by `unlock --file`.
+pub fn load_from_file(file_path: &std::path::Path) -> Result<TimeLockedMessage> {
+    let json = std::fs::read_to_string(file_path)
+        .map_err(|e| anyhow!("Failed to read file {:?}: {}", file_path, e))?;
+
+    let message: TimeLockedMessage = serde_json::from_str(&json)
+        .map_err(|e| anyhow!("Failed to parse JSON: {}", e))?;
+
+    Ok(message)
+}
@@ -0,0 +1,153 @@
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use aws_sdk_s3 as s3;
+use std::collections::HashMap;
+
+use super::CapsuleStore;
+use crate::crypto::TimeLockedMessage;
+
+/// S3-compatible backend, for keeping capsules on object storage (AWS S3,
+/// Garage, MinIO, ...) so they can be unlocked from a different machine than
+/// the one they were locked on.
+pub struct S3Store {
+    client: s3::Client,
+    bucket: String,
+}
+
+impl S3Store {
+    pub async fn new(
+        endpoint: Option<String>,
+        bucket: String,
+        region: String,
+        access_key: String,
+        secret_key: String,
+    ) -> Result<Self> {
+        let credentials = s3::config::Credentials::new(access_key, secret_key, None, None, "timecapsule");
+
+        let mut config_loader = aws_config::defaults(aws_config::BehaviorVersion::latest())
+            .region(s3::config::Region::new(region))
+            .credentials_provider(credentials);
+
+        if let Some(endpoint) = endpoint {
+            config_loader = config_loader.endpoint_url(endpoint);
+        }
+
+        let sdk_config = config_loader.load().await;
+        let s3_config = s3::config::Builder::from(&sdk_config)
+            .force_path_style(true)
+            .build();
+
+        Ok(S3Store {
+            client: s3::Client::from_conf(s3_config),
+            bucket,
+        })
+    }
+
+    fn key_for(&self, id: &str) -> String {
+        format!("{}.json", id)
+    }
+
+    async fn put_at(&self, id: &str, message: &TimeLockedMessage) -> Result<()> {
+        let json = serde_json::to_vec_pretty(message)?;
+
+        self.client
+            .put_object()
+            .bucket(&self.bucket)
+            .key(self.key_for(id))
+            .body(json.into())
+            .send()
+            .await
+            .map_err(|e| anyhow!("Failed to upload capsule {} to S3: {}", id, e))?;
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl CapsuleStore for S3Store {
+    async fn put(&self, message: &TimeLockedMessage) -> Result<String> {
+        let id = uuid::Uuid::new_v4().to_string();
+        self.put_at(&id, message).await?;
+        Ok(id)
+    }
+
+    async fn get(&self, id: &str) -> Result<TimeLockedMessage> {
+        let output = self
+            .client
+            .get_object()
+            .bucket(&self.bucket)
+            .key(self.key_for(id))
+            .send()
+            .await
+            .map_err(|e| anyhow!("Failed to fetch capsule {} from S3: {}", id, e))?;
+
+        let bytes = output
+            .body
+            .collect()
+            .await
+            .map_err(|e| anyhow!("Failed to read capsule {} body: {}", id, e))?
+            .into_bytes();
+
+        serde_json::from_slice(&bytes).map_err(|e| anyhow!("Failed to parse capsule {}: {}", id, e))
+    }
+
+    async fn list(&self) -> Result<HashMap<String, TimeLockedMessage>> {
+        let mut messages = HashMap::new();
+        let mut continuation_token = None;
+
+        loop {
+            let mut request = self.client.list_objects_v2().bucket(&self.bucket);
+            if let Some(token) = continuation_token {
+                request = request.continuation_token(token);
+            }
+
+            let response = request
+                .send()
+                .await
+                .map_err(|e| anyhow!("Failed to list capsules in S3: {}", e))?;
+
+            let ids: Vec<String> = response
+                .contents()
+                .iter()
+                .filter_map(|object| object.key()?.strip_suffix(".json").map(str::to_string))
+                .collect();
+
+            let fetches = ids.iter().map(|id| self.get(id));
+            let results = futures::future::join_all(fetches).await;
+
+            for (id, result) in ids.into_iter().zip(results) {
+                match result {
+                    Ok(message) => {
+                        messages.insert(id, message);
+                    }
+                    Err(e) => {
+                        eprintln!("Warning: Failed to load message {}: {}", id, e);
+                    }
+                }
+            }
+
+            continuation_token = response.next_continuation_token().map(str::to_string);
+            if continuation_token.is_none() {
+                break;
+            }
+        }
+
+        Ok(messages)
+    }
+
+    async fn update(&self, id: &str, message: &TimeLockedMessage) -> Result<()> {
+        self.put_at(id, message).await
+    }
+
+    async fn delete(&self, id: &str) -> Result<()> {
+        self.client
+            .delete_object()
+            .bucket(&self.bucket)
+            .key(self.key_for(id))
+            .send()
+            .await
+            .map_err(|e| anyhow!("Failed to delete capsule {} from S3: {}", id, e))?;
+
+        Ok(())
+    }
+}
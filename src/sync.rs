@@ -0,0 +1,352 @@
+use aes_gcm::{
+    aead::{Aead as _, KeyInit as _, OsRng},
+    Aes256Gcm, Key, Nonce,
+};
+use anyhow::{anyhow, Result};
+use argon2::password_hash::rand_core::RngCore;
+use base64::{engine::general_purpose, Engine as _};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+
+use crate::crypto::TimeLockedMessage;
+use crate::storage::CapsuleStore;
+
+/// One capsule as it travels over the wire. The remote only ever sees this
+/// sealed blob — never a capsule's plaintext, nor even its own (still
+/// password/recipient-encrypted) JSON structure, label or unlock date.
+#[derive(Serialize, Deserialize)]
+struct SyncEnvelope {
+    id: String,
+    nonce: String,
+    ciphertext: String,
+}
+
+/// Directory everything device-local (the sync key, the tombstone list)
+/// lives in. Honors `TIMECAPSULE_HOME` so tests (and anyone who wants an
+/// isolated profile) don't have to touch the real `$HOME`.
+fn data_dir() -> Result<PathBuf> {
+    if let Ok(override_dir) = std::env::var("TIMECAPSULE_HOME") {
+        return Ok(PathBuf::from(override_dir));
+    }
+
+    let home_dir = dirs::home_dir().ok_or_else(|| anyhow!("Could not find home directory"))?;
+    Ok(home_dir.join(".timecapsule"))
+}
+
+fn sync_key_path() -> Result<PathBuf> {
+    Ok(data_dir()?.join("key"))
+}
+
+fn tombstones_path() -> Result<PathBuf> {
+    Ok(data_dir()?.join("tombstones.json"))
+}
+
+fn read_tombstones(path: &PathBuf) -> Result<Vec<String>> {
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+
+    let json = fs::read_to_string(path)
+        .map_err(|e| anyhow!("Failed to read tombstones {:?}: {}", path, e))?;
+    serde_json::from_str(&json).map_err(|e| anyhow!("Tombstones file {:?} is corrupt: {}", path, e))
+}
+
+fn write_tombstones(path: &PathBuf, ids: &[String]) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let json = serde_json::to_string_pretty(ids)?;
+    fs::write(path, json).map_err(|e| anyhow!("Failed to write tombstones {:?}: {}", path, e))
+}
+
+/// Record that a capsule was deleted locally (e.g. a burn-after-read
+/// capsule hitting its last read) so the next `sync` tells the remote to
+/// forget it too, instead of letting another device's copy resurrect it.
+pub fn record_tombstone(id: &str) -> Result<()> {
+    let path = tombstones_path()?;
+    let mut ids = read_tombstones(&path)?;
+
+    if !ids.iter().any(|existing| existing == id) {
+        ids.push(id.to_string());
+        write_tombstones(&path, &ids)?;
+    }
+
+    Ok(())
+}
+
+/// Load this device's sync key, generating and persisting a new one on
+/// first use. Never overwrites a key that's already there.
+///
+/// To sync the same capsules across multiple devices, copy this file to
+/// each of them (out-of-band, like a restic/age repository key) before
+/// running `sync` — two devices with different keys can each push to the
+/// server but will never be able to unseal each other's envelopes.
+fn load_or_create_sync_key() -> Result<[u8; 32]> {
+    let path = sync_key_path()?;
+
+    if path.exists() {
+        // Best-effort: a key that's already readable shouldn't block sync
+        // just because we couldn't additionally lock down its permissions
+        // (e.g. it's owned by another user on this machine).
+        if let Err(e) = harden_key_permissions(&path) {
+            eprintln!("Warning: {}", e);
+        }
+
+        let encoded = fs::read_to_string(&path)
+            .map_err(|e| anyhow!("Failed to read sync key {:?}: {}", path, e))?;
+        let bytes = general_purpose::STANDARD
+            .decode(encoded.trim())
+            .map_err(|e| anyhow!("Sync key {:?} is corrupt: {}", path, e))?;
+        return bytes
+            .try_into()
+            .map_err(|_| anyhow!("Sync key {:?} must be 32 bytes", path));
+    }
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let mut key = [0u8; 32];
+    OsRng.fill_bytes(&mut key);
+    write_key_file(&path, &general_purpose::STANDARD.encode(key))?;
+
+    Ok(key)
+}
+
+/// Write the sync key with `0600` permissions from the moment it's created,
+/// rather than writing it world-readable and tightening permissions after
+/// the fact (which leaves a window where another local user could read it).
+#[cfg(unix)]
+fn write_key_file(path: &std::path::Path, contents: &str) -> Result<()> {
+    use std::io::Write as _;
+    use std::os::unix::fs::OpenOptionsExt as _;
+
+    let mut file = fs::OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .mode(0o600)
+        .open(path)
+        .map_err(|e| anyhow!("Failed to create sync key {:?}: {}", path, e))?;
+
+    file.write_all(contents.as_bytes())
+        .map_err(|e| anyhow!("Failed to write sync key {:?}: {}", path, e))
+}
+
+#[cfg(not(unix))]
+fn write_key_file(path: &std::path::Path, contents: &str) -> Result<()> {
+    fs::write(path, contents).map_err(|e| anyhow!("Failed to write sync key {:?}: {}", path, e))
+}
+
+/// Tighten permissions on a sync key that may have been created by an
+/// older build before this was enforced at creation time. A no-op if the
+/// mode is already what we want, to avoid a chmod syscall on every sync.
+#[cfg(unix)]
+fn harden_key_permissions(path: &std::path::Path) -> Result<()> {
+    use std::os::unix::fs::PermissionsExt as _;
+
+    let metadata = fs::metadata(path).map_err(|e| anyhow!("Failed to stat sync key {:?}: {}", path, e))?;
+    if metadata.permissions().mode() & 0o777 == 0o600 {
+        return Ok(());
+    }
+
+    fs::set_permissions(path, std::fs::Permissions::from_mode(0o600))
+        .map_err(|e| anyhow!("Failed to restrict permissions on sync key {:?}: {}", path, e))
+}
+
+#[cfg(not(unix))]
+fn harden_key_permissions(_path: &std::path::Path) -> Result<()> {
+    Ok(())
+}
+
+fn seal(key: &[u8; 32], id: &str, message: &TimeLockedMessage) -> Result<SyncEnvelope> {
+    let plaintext = serde_json::to_vec(message)?;
+
+    let mut nonce_bytes = [0u8; 12];
+    OsRng.fill_bytes(&mut nonce_bytes);
+
+    let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(key));
+    let ciphertext = cipher
+        .encrypt(Nonce::from_slice(&nonce_bytes), plaintext.as_ref())
+        .map_err(|e| anyhow!("Failed to seal capsule {} for sync: {}", id, e))?;
+
+    Ok(SyncEnvelope {
+        id: id.to_string(),
+        nonce: general_purpose::STANDARD.encode(nonce_bytes),
+        ciphertext: general_purpose::STANDARD.encode(ciphertext),
+    })
+}
+
+fn unseal(key: &[u8; 32], envelope: &SyncEnvelope) -> Result<TimeLockedMessage> {
+    let nonce_bytes = general_purpose::STANDARD
+        .decode(&envelope.nonce)
+        .map_err(|e| anyhow!("Failed to decode sync nonce for {}: {}", envelope.id, e))?;
+    let ciphertext = general_purpose::STANDARD
+        .decode(&envelope.ciphertext)
+        .map_err(|e| anyhow!("Failed to decode sync ciphertext for {}: {}", envelope.id, e))?;
+
+    let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(key));
+    let plaintext = cipher
+        .decrypt(Nonce::from_slice(&nonce_bytes), ciphertext.as_ref())
+        .map_err(|e| anyhow!("Failed to unseal capsule {}: {}", envelope.id, e))?;
+
+    serde_json::from_slice(&plaintext)
+        .map_err(|e| anyhow!("Capsule {} sync envelope was corrupt: {}", envelope.id, e))
+}
+
+/// What changed during a `sync` run, for the CLI to report back to the user.
+pub struct SyncReport {
+    pub pushed: usize,
+    pub pulled: usize,
+}
+
+/// Push every local capsule to `endpoint`, then pull down anything the
+/// remote has that's missing or newer locally. Capsules are addressed by
+/// their existing UUID, so pushing/pulling the same capsule twice is a
+/// no-op, and a conflicting id is resolved by keeping the newer `created_at`.
+///
+/// Deletions (e.g. a burn-after-read capsule self-destructing) are recorded
+/// locally by `record_tombstone` at delete time. Before anything else, this
+/// pushes a remote delete for every pending tombstone and excludes those
+/// ids from the pull below, so repeatedly syncing the *same* device can't
+/// resurrect a capsule it already deleted. A tombstone is only cleared once
+/// its remote delete actually succeeds, so a run that can't reach the
+/// server retries it next time instead of losing it.
+///
+/// This still doesn't reach every device: a capsule deleted on device A is
+/// only a tombstone in A's local `tombstones.json`, so device B — which
+/// never deleted its own copy — has nothing telling it not to re-push that
+/// copy on its next sync. Closing that gap needs the remote to understand
+/// tombstones as a first-class concept (so a pull can tell "never existed"
+/// apart from "existed and was deleted"), which is out of reach of a
+/// client that only speaks a generic GET/PUT/DELETE capsule API.
+pub async fn run(store: &dyn CapsuleStore, endpoint: &str) -> Result<SyncReport> {
+    let key = load_or_create_sync_key()?;
+    let client = reqwest::Client::new();
+    let base = endpoint.trim_end_matches('/');
+
+    let tombstones_path = tombstones_path()?;
+    let tombstones = read_tombstones(&tombstones_path)?;
+
+    let mut remaining_tombstones = Vec::new();
+    for id in &tombstones {
+        let pushed = client.delete(format!("{}/capsules/{}", base, id)).send().await;
+        match pushed {
+            Ok(response) if response.status().is_success() || response.status() == reqwest::StatusCode::NOT_FOUND => {}
+            _ => remaining_tombstones.push(id.clone()),
+        }
+    }
+    write_tombstones(&tombstones_path, &remaining_tombstones)?;
+    let tombstones = remaining_tombstones;
+
+    let local = store.list().await?;
+
+    let remote_envelopes: Vec<SyncEnvelope> = client
+        .get(format!("{}/capsules", base))
+        .send()
+        .await
+        .map_err(|e| anyhow!("Failed to reach sync server: {}", e))?
+        .error_for_status()
+        .map_err(|e| anyhow!("Sync server returned an error: {}", e))?
+        .json()
+        .await
+        .map_err(|e| anyhow!("Sync server sent an unexpected response: {}", e))?;
+
+    let mut remote = HashMap::new();
+    for envelope in &remote_envelopes {
+        if tombstones.contains(&envelope.id) {
+            // Still pending deletion (the push above failed) — don't let it
+            // come back locally just because we couldn't remove it remotely yet.
+            continue;
+        }
+
+        match unseal(&key, envelope) {
+            Ok(message) => {
+                remote.insert(envelope.id.clone(), message);
+            }
+            Err(e) => eprintln!("Warning: could not unseal remote capsule {}: {}", envelope.id, e),
+        }
+    }
+
+    let mut pushed = 0;
+    for (id, message) in &local {
+        let should_push = match remote.get(id) {
+            Some(remote_message) => message.created_at > remote_message.created_at,
+            None => true,
+        };
+
+        if should_push {
+            let envelope = seal(&key, id, message)?;
+            client
+                .put(format!("{}/capsules/{}", base, id))
+                .json(&envelope)
+                .send()
+                .await
+                .map_err(|e| anyhow!("Failed to push capsule {}: {}", id, e))?
+                .error_for_status()
+                .map_err(|e| anyhow!("Sync server rejected capsule {}: {}", id, e))?;
+            pushed += 1;
+        }
+    }
+
+    let mut pulled = 0;
+    for (id, message) in remote {
+        let should_pull = match local.get(&id) {
+            Some(local_message) => message.created_at > local_message.created_at,
+            None => true,
+        };
+
+        if should_pull {
+            store.update(&id, &message).await?;
+            pulled += 1;
+        }
+    }
+
+    Ok(SyncReport { pushed, pulled })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // TIMECAPSULE_HOME is process-global, so any test that overrides it must
+    // hold this lock for the duration — cargo test runs tests on multiple
+    // threads in the same process by default. Recover from poisoning rather
+    // than propagate it, so one failing test doesn't take the rest down too.
+    static HOME_OVERRIDE: std::sync::Mutex<()> = std::sync::Mutex::new(());
+
+    /// Clears TIMECAPSULE_HOME and removes its temp directory on drop, so a
+    /// panicking assertion still cleans up instead of leaking process-global
+    /// state into whatever test runs next.
+    struct HomeOverride(PathBuf);
+
+    impl HomeOverride {
+        fn set() -> Self {
+            let home = std::env::temp_dir().join(format!("timecapsule-test-home-{}", uuid::Uuid::new_v4()));
+            std::env::set_var("TIMECAPSULE_HOME", &home);
+            HomeOverride(home)
+        }
+    }
+
+    impl Drop for HomeOverride {
+        fn drop(&mut self) {
+            std::env::remove_var("TIMECAPSULE_HOME");
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    #[test]
+    fn recording_a_tombstone_twice_does_not_duplicate_it() {
+        let _lock = HOME_OVERRIDE.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let _home = HomeOverride::set();
+
+        record_tombstone("test-tombstone-id").unwrap();
+        record_tombstone("test-tombstone-id").unwrap();
+
+        let ids = read_tombstones(&tombstones_path().unwrap()).unwrap();
+        assert_eq!(ids.iter().filter(|id| *id == "test-tombstone-id").count(), 1);
+    }
+}